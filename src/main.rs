@@ -1,61 +1,71 @@
 use std::env;
-use std::path::Path;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 
-use ignore::types::TypesBuilder;
-use ignore::WalkBuilder;
-use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use clap::Parser;
 use warp::Filter;
 
-const ROOT_ISO_PATH: &str = "/mnt/storage/games/psp";
-const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ');
+/// Size of a single block cache entry.
+const BLOCK_SIZE: u64 = 256 * 1024;
+/// Total bytes of ISO data the block cache is allowed to hold in memory.
+const CACHE_BUDGET_BYTES: u64 = 256 * 1024 * 1024;
 
-const RUMD_LISTENING_PORT: u16 = 41041;
 const RUMD_VERSION: &str = "rumd v0.1.0";
 
+/// Serves PSP ISOs over HTTP range requests, so a PSP can seek through one
+/// without downloading it whole.
+#[derive(Parser, Debug)]
+#[command(name = "rumd", version = RUMD_VERSION)]
+struct Args {
+    /// Directory to index for `*.iso` files.
+    #[arg(long, env = "RUMD_ISO_ROOT", default_value = "/mnt/storage/games/psp")]
+    iso_root: PathBuf,
+
+    /// Address to bind the HTTP server to.
+    #[arg(long, env = "RUMD_BIND", default_value = "0.0.0.0:41041")]
+    bind: SocketAddr,
+
+    /// Log level, e.g. `info`, `debug`, `trace`.
+    #[arg(long, env = "RUMD_LOG_LEVEL", default_value = "info")]
+    log_level: String,
+}
+
 #[tokio::main]
 async fn main() {
+    let args = Args::parse();
+
     if env::var_os("RUST_LOG").is_none() {
-        // Set `RUST_LOG=rumd=debug` to see debug logs,
-        // this only shows access logs.
-        env::set_var("RUST_LOG", "rumd=info");
+        env::set_var("RUST_LOG", format!("rumd={}", args.log_level));
     }
     pretty_env_logger::init();
 
-    let mut builder = TypesBuilder::new();
-    builder.add("iso", "*.iso").unwrap();
-    builder.select("iso");
-    let matcher = builder.build().unwrap();
-
-    for result in WalkBuilder::new(ROOT_ISO_PATH).types(matcher).build() {
-        match result {
-            Ok(entry) => {
-                let path: &Path = entry.path().strip_prefix(ROOT_ISO_PATH).unwrap();
-                println!("/{}", utf8_percent_encode(path.to_str().unwrap(), FRAGMENT));
-            }
-            Err(err) => println!("ERROR: {}", err),
-        }
+    if !args.iso_root.is_dir() {
+        eprintln!(
+            "error: --iso-root '{}' does not exist or is not a directory",
+            args.iso_root.display()
+        );
+        std::process::exit(1);
     }
 
-    // Must Have
-    // TODO(meteorfox): Build KV "database" of flat ISO filenames map to their entry
-    // TODO(meteorfox): Each entry contains file length info and file-system path
-    // TODO(meteorfox): When reading a range of bytes, look up in KV database, check
-    //                  range within limits, open file and read bytes, close file then
-    //                  return bytes.
+    let catalog = std::sync::Arc::new(rumd::build_catalog(&args.iso_root));
+    log::info!(
+        "indexed {} ISO(s) under {}",
+        catalog.len(),
+        args.iso_root.display()
+    );
+
+    let cache = rumd::BlockCache::shared(CACHE_BUDGET_BYTES);
 
     // Nice to Have
     // TODO(meteorfox): Validate that they are actually valid PSP ISO files
-    // TODO(meteorfox): Keep cache of blocks in memory, if necessary.
 
     let server_header = warp::reply::with::default_header("Server", RUMD_VERSION);
 
-    let api = filters::rumd();
+    let api = filters::rumd(catalog, cache);
 
     let routes = api.with(warp::log("rumd")).with(&server_header);
 
-    warp::serve(routes)
-        .run(([10, 0, 0, 184], RUMD_LISTENING_PORT))
-        .await;
+    warp::serve(routes).run(args.bind).await;
 }
 
 mod filters {
@@ -63,122 +73,670 @@ mod filters {
 
     use warp::Filter;
 
-    pub fn rumd() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        umd_list().or(umd_info()).or(umd_read())
+    pub fn rumd(
+        catalog: rumd::Catalog,
+        cache: rumd::SharedBlockCache,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        umd_list(catalog.clone())
+            .or(umd_info(catalog.clone()))
+            .or(umd_read(catalog, cache))
+    }
+
+    fn with_catalog(
+        catalog: rumd::Catalog,
+    ) -> impl Filter<Extract = (rumd::Catalog,), Error = std::convert::Infallible> + Clone {
+        warp::any().map(move || catalog.clone())
+    }
+
+    fn with_cache(
+        cache: rumd::SharedBlockCache,
+    ) -> impl Filter<Extract = (rumd::SharedBlockCache,), Error = std::convert::Infallible> + Clone
+    {
+        warp::any().map(move || cache.clone())
     }
 
     /// GET /
-    pub fn umd_list() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
-        warp::path::end().and(warp::get()).and_then(rumd::list_umds)
+    pub fn umd_list(
+        catalog: rumd::Catalog,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        warp::path::end()
+            .and(warp::get())
+            .and(with_catalog(catalog))
+            .and_then(rumd::list_umds)
     }
 
     /// HEAD /<umd_name:string>
-    pub fn umd_info() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    pub fn umd_info(
+        catalog: rumd::Catalog,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+        let if_modified_since = warp::header::optional::<String>("if-modified-since");
         warp::path::param()
             .and(warp::head())
+            .and(if_modified_since)
+            .and(with_catalog(catalog))
             .and_then(rumd::info_umd)
     }
 
     /// GET /<umd_name:string>
-    pub fn umd_read() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    pub fn umd_read(
+        catalog: rumd::Catalog,
+        cache: rumd::SharedBlockCache,
+    ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
         let range_header = warp::header::<rumd::Range>("range");
+        let if_range = warp::header::optional::<String>("if-range");
         warp::path::param()
             .and(warp::get())
             .and(range_header)
+            .and(if_range)
+            .and(with_catalog(catalog))
+            .and(with_cache(cache))
             .and_then(rumd::read_umd)
     }
 }
 
 mod rumd {
-    use std::num::ParseIntError;
+    use std::collections::{HashMap, VecDeque};
+    use std::fs;
+    use std::path::{Path, PathBuf};
     use std::str::FromStr;
-
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use async_stream::stream;
+    use bytes::Bytes;
+    use futures::Stream;
+    use ignore::types::TypesBuilder;
+    use ignore::WalkBuilder;
+    use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+    use tokio::fs::File;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+    use tokio::sync::Mutex;
     use warp::http::{Response, StatusCode};
-    use warp::hyper;
+    use warp::hyper::{self, Body};
+
+    const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ');
+
+    /// A single indexed ISO: where it lives on disk, how large it is, and
+    /// when it was last modified (used to derive `Last-Modified`/`ETag`).
+    #[derive(Debug, Clone)]
+    pub struct IsoEntry {
+        pub path: PathBuf,
+        pub len: u64,
+        pub modified: SystemTime,
+    }
+
+    impl IsoEntry {
+        /// Weak `ETag` derived from length and mtime, truncated to whole
+        /// seconds to match the resolution of `Last-Modified`/`If-Range`.
+        fn etag(&self) -> String {
+            format!("W/\"{}-{}\"", self.len, self.modified_secs())
+        }
+
+        /// `Last-Modified`, formatted per RFC 7231 §7.1.1.1.
+        fn last_modified(&self) -> String {
+            httpdate::fmt_http_date(self.truncated_modified())
+        }
+
+        fn modified_secs(&self) -> u64 {
+            self.modified
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        }
+
+        fn truncated_modified(&self) -> SystemTime {
+            UNIX_EPOCH + Duration::from_secs(self.modified_secs())
+        }
+
+        /// Whether this entry is unchanged as of an `If-Modified-Since` or
+        /// `If-Range` date, i.e. it has not been modified more recently.
+        fn not_modified_since(&self, since: SystemTime) -> bool {
+            self.truncated_modified() <= since
+        }
+
+        /// Whether a client-supplied `If-Range` validator (an `ETag` or an
+        /// HTTP-date) still matches this entry.
+        fn matches_if_range(&self, validator: &str) -> bool {
+            if validator == self.etag() {
+                return true;
+            }
+            httpdate::parse_http_date(validator)
+                .map(|date| self.truncated_modified() == date)
+                .unwrap_or(false)
+        }
+    }
 
-    pub async fn list_umds() -> Result<impl warp::Reply, warp::Rejection> {
-        let isos = vec![
-            "/",
-            "/Crisis%20Core%20-%20Final%20Fantasy%20VII%20(USA).iso",
-            "/Metal_Gear_Solid_Peace_Walker_USA_PSP-pSyPSP.iso",
-            "/Monster%20Hunter%20Freedom%20Unite%20(USA)%20(En,Fr,De,Es,It).iso",
-        ];
-        Ok(isos.join("\n"))
+    /// Shared, read-only map of percent-encoded ISO name to its [`IsoEntry`].
+    pub type Catalog = Arc<HashMap<String, IsoEntry>>;
+
+    /// Identifies one fixed-size block of an ISO: its percent-encoded
+    /// catalog name and its zero-based index within the file.
+    type BlockKey = (String, u64);
+
+    /// Bounded, in-memory LRU cache of ISO blocks, so repeated seeks into
+    /// hot regions (menus, frequently-loaded assets) avoid hitting disk.
+    ///
+    /// Guarded by a `tokio::sync::Mutex` and shared across requests via
+    /// [`SharedBlockCache`].
+    pub struct BlockCache {
+        block_size: u64,
+        budget_bytes: u64,
+        used_bytes: u64,
+        blocks: HashMap<BlockKey, Bytes>,
+        // Access order, least-recently-used first.
+        order: VecDeque<BlockKey>,
     }
 
-    pub async fn info_umd(umd_name: String) -> Result<impl warp::Reply, warp::Rejection> {
+    pub type SharedBlockCache = Arc<Mutex<BlockCache>>;
+
+    impl BlockCache {
+        pub fn new(block_size: u64, budget_bytes: u64) -> Self {
+            BlockCache {
+                block_size,
+                budget_bytes,
+                used_bytes: 0,
+                blocks: HashMap::new(),
+                order: VecDeque::new(),
+            }
+        }
+
+        /// Builds a cache using the crate-wide block size and wraps it for
+        /// sharing across request handlers.
+        pub fn shared(budget_bytes: u64) -> SharedBlockCache {
+            Arc::new(Mutex::new(BlockCache::new(super::BLOCK_SIZE, budget_bytes)))
+        }
+
+        fn get(&mut self, key: &BlockKey) -> Option<Bytes> {
+            let block = self.blocks.get(key).cloned()?;
+            self.order.retain(|k| k != key);
+            self.order.push_back(key.clone());
+            Some(block)
+        }
+
+        fn insert(&mut self, key: BlockKey, block: Bytes) {
+            if let Some(old) = self.blocks.insert(key.clone(), block.clone()) {
+                self.used_bytes -= old.len() as u64;
+                self.order.retain(|k| k != &key);
+            }
+            self.used_bytes += block.len() as u64;
+            self.order.push_back(key);
+
+            while self.used_bytes > self.budget_bytes {
+                match self.order.pop_front() {
+                    Some(evicted_key) => {
+                        if let Some(evicted) = self.blocks.remove(&evicted_key) {
+                            self.used_bytes -= evicted.len() as u64;
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    /// Walks `root` for `*.iso` files and indexes each one under its
+    /// percent-encoded path (relative to `root`), mirroring the names
+    /// handed out by [`list_umds`].
+    pub fn build_catalog(root: &Path) -> HashMap<String, IsoEntry> {
+        let mut builder = TypesBuilder::new();
+        builder.add("iso", "*.iso").unwrap();
+        builder.select("iso");
+        let matcher = builder.build().unwrap();
+
+        let mut catalog = HashMap::new();
+        for result in WalkBuilder::new(root).types(matcher).build() {
+            match result {
+                Ok(entry) if entry.file_type().is_some_and(|ft| ft.is_file()) => {
+                    let path = entry.path();
+                    let relative = path.strip_prefix(root).unwrap();
+                    let name =
+                        utf8_percent_encode(relative.to_str().unwrap(), FRAGMENT).to_string();
+
+                    match fs::metadata(path).and_then(|m| Ok((m.len(), m.modified()?))) {
+                        Ok((len, modified)) => {
+                            catalog.insert(
+                                name,
+                                IsoEntry {
+                                    path: path.to_path_buf(),
+                                    len,
+                                    modified,
+                                },
+                            );
+                        }
+                        Err(err) => log::error!("{}: {}", path.display(), err),
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => log::error!("{}", err),
+            }
+        }
+        catalog
+    }
+
+    pub async fn list_umds(catalog: Catalog) -> Result<impl warp::Reply, warp::Rejection> {
+        let mut names: Vec<&String> = catalog.keys().collect();
+        names.sort();
+        let body = names
+            .iter()
+            .map(|name| format!("/{}", name))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(body)
+    }
+
+    pub async fn info_umd(
+        umd_name: String,
+        if_modified_since: Option<String>,
+        catalog: Catalog,
+    ) -> Result<impl warp::Reply, warp::Rejection> {
         log::debug!("info UMD: path={}", umd_name);
 
+        let entry = catalog.get(&umd_name).ok_or_else(warp::reject::not_found)?;
+
+        let unmodified = if_modified_since
+            .as_deref()
+            .and_then(|since| httpdate::parse_http_date(since).ok())
+            .is_some_and(|since| entry.not_modified_since(since));
+
+        if unmodified {
+            let resp = Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header("Last-Modified", entry.last_modified())
+                .header("ETag", entry.etag())
+                .body(hyper::Body::empty());
+            return Ok(resp);
+        }
+
         let resp = Response::builder()
             .header("Accept-Ranges", "bytes")
             .header("Content-Type", "application/octet-stream")
-            .header("Content-Length", "1646002176")
+            .header("Content-Length", entry.len.to_string())
+            .header("Last-Modified", entry.last_modified())
+            .header("ETag", entry.etag())
             .body(hyper::Body::empty());
         Ok(resp)
     }
 
+    /// Reads one `BLOCK_SIZE`-aligned block starting at `block_start` from
+    /// an already-open `file`, seeking to it first. The final block of a
+    /// file is shorter than `BLOCK_SIZE`, so the returned `Bytes` may be
+    /// smaller than it.
+    async fn read_block(
+        file: &mut File,
+        block_start: u64,
+        block_size: u64,
+    ) -> std::io::Result<Bytes> {
+        file.seek(std::io::SeekFrom::Start(block_start)).await?;
+
+        let mut buf = vec![0u8; block_size as usize];
+        let mut filled = 0;
+        while filled < buf.len() {
+            match file.read(&mut buf[filled..]).await {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) => return Err(err),
+            }
+        }
+        buf.truncate(filled);
+        Ok(Bytes::from(buf))
+    }
+
+    /// Streams the inclusive byte range `[start, end]` of `iso_name`,
+    /// serving each overlapping block from `cache` when present and
+    /// reading it through from disk (then populating the cache) otherwise.
+    ///
+    /// `path` is opened once and held for the lifetime of the stream, so a
+    /// range spanning many blocks reuses one file handle (seeking between
+    /// reads) instead of reopening the file per block.
+    fn read_range(
+        iso_name: String,
+        path: PathBuf,
+        start: u64,
+        end: u64,
+        cache: SharedBlockCache,
+    ) -> impl Stream<Item = std::io::Result<Bytes>> {
+        stream! {
+            let mut file = match File::open(&path).await {
+                Ok(file) => file,
+                Err(err) => {
+                    yield Err(err);
+                    return;
+                }
+            };
+
+            let block_size = cache.lock().await.block_size;
+            let mut offset = start;
+            while offset <= end {
+                let block_index = offset / block_size;
+                let block_start = block_index * block_size;
+                let key = (iso_name.clone(), block_index);
+
+                let cached = cache.lock().await.get(&key);
+                let block = match cached {
+                    Some(block) => block,
+                    None => match read_block(&mut file, block_start, block_size).await {
+                        Ok(block) => {
+                            cache.lock().await.insert(key, block.clone());
+                            block
+                        }
+                        Err(err) => {
+                            yield Err(err);
+                            return;
+                        }
+                    },
+                };
+
+                let slice_start = (offset - block_start) as usize;
+                let slice_end = std::cmp::min(block.len() as u64, end - block_start + 1) as usize;
+                if slice_start < slice_end {
+                    yield Ok(block.slice(slice_start..slice_end));
+                }
+                offset = block_start + block_size;
+            }
+        }
+    }
+
     pub async fn read_umd(
         umd_name: String,
         range: Range,
+        if_range: Option<String>,
+        catalog: Catalog,
+        cache: SharedBlockCache,
     ) -> Result<impl warp::Reply, warp::Rejection> {
-        log::debug!(
-            "Read UMD: path={} range=bytes {}-{}",
-            umd_name,
-            range.start,
-            range.end
-        );
+        log::debug!("Read UMD: path={} range={:?}", umd_name, range);
+
+        let entry = catalog.get(&umd_name).ok_or_else(warp::reject::not_found)?;
+        let total_len = entry.len;
+
+        // A stale `If-Range` means the file changed since the client started;
+        // fall back to a full 200 response instead of honoring the range.
+        let range_is_fresh = if_range
+            .as_deref()
+            .is_none_or(|validator| entry.matches_if_range(validator));
+
+        // The fallback full representation is just the range `bytes=0-`,
+        // resolved the same length-aware way as an honored range, so a
+        // zero-length file doesn't fall through to a bogus `Content-Length`.
+        let (status, start, end, to_send) = if range_is_fresh {
+            match range.resolve(total_len) {
+                Some((start, end)) => (StatusCode::PARTIAL_CONTENT, start, end, end - start + 1),
+                None => {
+                    let resp = Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header("Content-Range", format!("bytes */{}", total_len))
+                        .header("Last-Modified", entry.last_modified())
+                        .header("ETag", entry.etag())
+                        .body(Body::empty());
+                    return Ok(resp);
+                }
+            }
+        } else {
+            match Range::Open(0).resolve(total_len) {
+                Some((start, end)) => (StatusCode::OK, start, end, end - start + 1),
+                None => (StatusCode::OK, 0, 0, 0),
+            }
+        };
 
-        let resp = Response::builder()
-            .status(StatusCode::PARTIAL_CONTENT)
+        let body = read_range(umd_name.clone(), entry.path.clone(), start, end, cache);
+
+        let mut builder = Response::builder()
+            .status(status)
             .header("Accept-Ranges", "bytes")
             .header("Content-Type", "application/octet-stream")
-            .header(
+            .header("Content-Length", to_send.to_string())
+            .header("Last-Modified", entry.last_modified())
+            .header("ETag", entry.etag());
+
+        if status == StatusCode::PARTIAL_CONTENT {
+            builder = builder.header(
                 "Content-Range",
-                format!("bytes {}-{}/{}", range.start, range.end, 1646002176),
-            )
-            .body(hyper::Body::empty());
+                format!("bytes {}-{}/{}", start, end, total_len),
+            );
+        }
+
+        let resp = builder.body(Body::wrap_stream(body));
 
         Ok(resp)
     }
 
+    /// A single-range `Range: bytes=...` request header, per RFC 7233 §2.1.
+    ///
+    /// Parsing only validates syntax; the range is resolved against the
+    /// resource's actual length (via [`Range::resolve`]) once that is known.
+    #[derive(Debug, PartialEq)]
+    pub enum Range {
+        /// `bytes=A-B`, both bounds given.
+        Normal(u64, u64),
+        /// `bytes=A-`, open-ended: from `A` through the end of the resource.
+        Open(u64),
+        /// `bytes=-N`, suffix: the last `N` bytes of the resource.
+        Suffix(u64),
+    }
+
     #[derive(Debug, PartialEq)]
-    pub struct Range {
-        start: i64,
-        end: i64,
+    pub struct RangeParseError;
+
+    impl Range {
+        /// Resolves this range against a resource of `len` bytes, returning
+        /// inclusive `(start, end)` bounds clamped to `len`, or `None` if the
+        /// range cannot be satisfied (RFC 7233 §4.4).
+        pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+            match *self {
+                Range::Normal(start, end) => {
+                    if len == 0 || start >= len || start > end {
+                        None
+                    } else {
+                        Some((start, std::cmp::min(end, len - 1)))
+                    }
+                }
+                Range::Open(start) => {
+                    if len == 0 || start >= len {
+                        None
+                    } else {
+                        Some((start, len - 1))
+                    }
+                }
+                Range::Suffix(suffix_len) => {
+                    if len == 0 || suffix_len == 0 {
+                        None
+                    } else {
+                        Some((len.saturating_sub(suffix_len), len - 1))
+                    }
+                }
+            }
+        }
     }
 
     impl FromStr for Range {
-        type Err = ParseIntError;
+        type Err = RangeParseError;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let range: Vec<&str> = s.trim_start_matches("bytes=").split('-').collect();
-            let start_from_str = range[0].parse::<i64>()?;
-            let end_from_str = range[1].parse::<i64>()?;
-            Ok(Range {
-                start: start_from_str,
-                end: end_from_str,
-            })
+            let spec = s.trim_start_matches("bytes=");
+            let (start_str, end_str) = spec.split_once('-').ok_or(RangeParseError)?;
+
+            if start_str.is_empty() {
+                let suffix_len = end_str.parse::<u64>().map_err(|_| RangeParseError)?;
+                return Ok(Range::Suffix(suffix_len));
+            }
+
+            let start = start_str.parse::<u64>().map_err(|_| RangeParseError)?;
+            if end_str.is_empty() {
+                return Ok(Range::Open(start));
+            }
+
+            let end = end_str.parse::<u64>().map_err(|_| RangeParseError)?;
+            Ok(Range::Normal(start, end))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn range_parses_normal() {
+            assert_eq!("bytes=0-499".parse::<Range>(), Ok(Range::Normal(0, 499)));
+        }
+
+        #[test]
+        fn range_parses_open_ended() {
+            assert_eq!("bytes=500-".parse::<Range>(), Ok(Range::Open(500)));
+        }
+
+        #[test]
+        fn range_parses_suffix() {
+            assert_eq!("bytes=-500".parse::<Range>(), Ok(Range::Suffix(500)));
+        }
+
+        #[test]
+        fn range_rejects_malformed_header() {
+            assert!("not-a-range".parse::<Range>().is_err());
+            assert!("bytes=abc-def".parse::<Range>().is_err());
+            assert!("bytes=".parse::<Range>().is_err());
+        }
+
+        #[test]
+        fn resolve_normal_clamps_end_to_len() {
+            assert_eq!(Range::Normal(0, 999).resolve(500), Some((0, 499)));
+        }
+
+        #[test]
+        fn resolve_normal_rejects_inverted_bounds() {
+            assert_eq!(Range::Normal(100, 50).resolve(500), None);
+        }
+
+        #[test]
+        fn resolve_rejects_start_past_eof() {
+            assert_eq!(Range::Normal(500, 600).resolve(500), None);
+            assert_eq!(Range::Open(500).resolve(500), None);
+        }
+
+        #[test]
+        fn resolve_open_ended_runs_to_eof() {
+            assert_eq!(Range::Open(100).resolve(500), Some((100, 499)));
+        }
+
+        #[test]
+        fn resolve_suffix_clamps_to_start_of_file() {
+            assert_eq!(Range::Suffix(1000).resolve(500), Some((0, 499)));
+            assert_eq!(Range::Suffix(100).resolve(500), Some((400, 499)));
+        }
+
+        #[test]
+        fn resolve_rejects_empty_resource() {
+            assert_eq!(Range::Normal(0, 0).resolve(0), None);
+            assert_eq!(Range::Open(0).resolve(0), None);
+            assert_eq!(Range::Suffix(10).resolve(0), None);
+        }
+
+        fn test_entry(len: u64, modified_secs: u64) -> IsoEntry {
+            IsoEntry {
+                path: PathBuf::from("/dev/null"),
+                len,
+                modified: UNIX_EPOCH + Duration::from_secs(modified_secs),
+            }
+        }
+
+        #[test]
+        fn etag_matches_if_range_validator() {
+            let entry = test_entry(1024, 1_700_000_000);
+            assert!(entry.matches_if_range(&entry.etag()));
+            assert!(!entry.matches_if_range("W/\"0-0\""));
+        }
+
+        #[test]
+        fn last_modified_matches_if_range_validator() {
+            let entry = test_entry(1024, 1_700_000_000);
+            assert!(entry.matches_if_range(&entry.last_modified()));
+        }
+
+        #[test]
+        fn not_modified_since_compares_truncated_mtime() {
+            let entry = test_entry(1024, 1_700_000_000);
+            assert!(entry.not_modified_since(entry.truncated_modified()));
+            assert!(!entry.not_modified_since(UNIX_EPOCH));
+        }
+
+        #[test]
+        fn block_cache_hits_after_insert() {
+            let mut cache = BlockCache::new(4, 1024);
+            let key = ("iso".to_string(), 0);
+            cache.insert(key.clone(), Bytes::from_static(b"abcd"));
+            assert_eq!(cache.get(&key), Some(Bytes::from_static(b"abcd")));
+        }
+
+        #[test]
+        fn block_cache_evicts_least_recently_used() {
+            let mut cache = BlockCache::new(4, 8);
+            let block0 = ("iso".to_string(), 0);
+            let block1 = ("iso".to_string(), 1);
+            let block2 = ("iso".to_string(), 2);
+
+            cache.insert(block0.clone(), Bytes::from_static(b"aaaa"));
+            cache.insert(block1.clone(), Bytes::from_static(b"bbbb"));
+            // Touching block 0 makes block 1 the least-recently-used entry.
+            assert!(cache.get(&block0).is_some());
+            // Budget only holds two blocks, so inserting a third evicts block 1.
+            cache.insert(block2.clone(), Bytes::from_static(b"cccc"));
+
+            assert!(cache.get(&block0).is_some());
+            assert!(cache.get(&block1).is_none());
+            assert!(cache.get(&block2).is_some());
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
     use super::filters;
+    use super::rumd::IsoEntry;
 
     use warp::http::StatusCode;
     use warp::test::request;
 
+    fn test_catalog() -> super::rumd::Catalog {
+        let mut catalog = HashMap::new();
+        catalog.insert(
+            "Crisis%20Core%20-%20Final%20Fantasy%20VII%20(USA).iso".to_string(),
+            IsoEntry {
+                path: PathBuf::from("/dev/null"),
+                len: 1646002176,
+                modified: std::time::SystemTime::UNIX_EPOCH,
+            },
+        );
+        catalog.insert(
+            "Metal_Gear_Solid_Peace_Walker_USA_PSP-pSyPSP.iso".to_string(),
+            IsoEntry {
+                path: PathBuf::from("/dev/null"),
+                len: 1646002176,
+                modified: std::time::SystemTime::UNIX_EPOCH,
+            },
+        );
+        catalog.insert(
+            "Monster%20Hunter%20Freedom%20Unite%20(USA)%20(En,Fr,De,Es,It).iso".to_string(),
+            IsoEntry {
+                path: PathBuf::from("/dev/null"),
+                len: 1646002176,
+                modified: std::time::SystemTime::UNIX_EPOCH,
+            },
+        );
+        Arc::new(catalog)
+    }
+
     #[tokio::test]
     async fn test_list() {
-        let api = filters::rumd();
+        let cache = super::rumd::BlockCache::shared(super::CACHE_BUDGET_BYTES);
+        let api = filters::rumd(test_catalog(), cache);
         let resp = request().method("GET").path("/").reply(&api).await;
 
         assert_eq!(resp.status(), StatusCode::OK);
         assert_eq!(
             resp.body(),
-            r#"/
-/Crisis%20Core%20-%20Final%20Fantasy%20VII%20(USA).iso
+            r#"/Crisis%20Core%20-%20Final%20Fantasy%20VII%20(USA).iso
 /Metal_Gear_Solid_Peace_Walker_USA_PSP-pSyPSP.iso
 /Monster%20Hunter%20Freedom%20Unite%20(USA)%20(En,Fr,De,Es,It).iso"#
         );